@@ -1,23 +1,90 @@
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode}, execute, terminal::{disable_raw_mode, enable_raw_mode, Clear, EnterAlternateScreen, LeaveAlternateScreen}
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event as CEvent, KeyCode, KeyEvent}, execute, terminal::{disable_raw_mode, enable_raw_mode, Clear, EnterAlternateScreen, LeaveAlternateScreen}
 };
 
-use std::{error::Error, io, time::{Duration, Instant}};
+use std::{
+    error::Error,
+    io,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
 
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    symbols,
     text::{Span, Spans},
-    widgets::{Block, Borders, Paragraph, Tabs},
+    widgets::{Block, Borders, Gauge, LineGauge, List, ListItem, Tabs},
     Frame, Terminal,
 };
 
-#[derive(Copy, Clone)]
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+
+// Max size of a single inventory stack, equal to u8::MAX since quantities
+// are stored as u8 — there's no 256th unit to hold, so a full stack reads
+// "255/255" in the UI, not "/256".
+const STACK_CAP: u8 = 255;
+
+// Index of the crafting tab within `App::titles`.
+const CRAFT_TAB: usize = 4;
+
+// Save file for the `persistence` feature; relative to the working
+// directory the game is launched from.
+#[cfg(feature = "persistence")]
+const SAVE_PATH: &str = "flux_survivalist_save.json";
+
+/// Events delivered to the main loop over the input channel: either a key
+/// the player pressed, or a tick fired on a fixed schedule by the input
+/// thread. Keeping both on one channel lets `run_app` stay a single
+/// `recv()` loop instead of juggling polling and timing itself.
+enum Event {
+    Input(KeyEvent),
+    Tick,
+}
+
+/// Spawns the background thread that owns all terminal input polling.
+/// It forwards key presses as `Event::Input` and emits `Event::Tick`
+/// whenever `tick_rate` elapses, so the main loop never blocks on
+/// `crossterm::event::poll` and rendering stays independent of input.
+fn spawn_input_thread(tick_rate: Duration) -> mpsc::Receiver<Event> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut last_tick = Instant::now();
+        loop {
+            let timeout = tick_rate
+                .checked_sub(last_tick.elapsed())
+                .unwrap_or_else(|| Duration::from_secs(0));
+
+            if event::poll(timeout).unwrap_or(false) {
+                if let Ok(CEvent::Key(key)) = event::read() {
+                    if tx.send(Event::Input(key)).is_err() {
+                        return;
+                    }
+                }
+            }
+
+            if last_tick.elapsed() >= tick_rate {
+                if tx.send(Event::Tick).is_err() {
+                    return;
+                }
+                last_tick = Instant::now();
+            }
+        }
+    });
+    rx
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
 enum Item {
     Wood,
     Fibre,
     Water,
+    Rope,
+    Campfire,
 }
 
 impl Item {
@@ -26,15 +93,55 @@ impl Item {
             Item::Wood => "wood",
             Item::Fibre => "fibre",
             Item::Water => "water",
+            Item::Rope => "rope",
+            Item::Campfire => "campfire",
         }
     }
 }
 
+#[derive(Clone)]
+struct Recipe {
+    name: &'static str,
+    inputs: Vec<(Item, u8)>,
+    output: (Item, u8),
+}
+
+/// An in-progress gathering job started from a resource tab. Advanced one
+/// tick at a time in `App::on_tick`; once `elapsed` reaches `duration` the
+/// job yields its item into the inventory and is dropped.
+#[derive(Clone)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+struct GatherJob {
+    item: Item,
+    elapsed: u8,
+    duration: u8,
+}
+
+impl GatherJob {
+    fn ratio(&self) -> f64 {
+        self.elapsed as f64 / self.duration as f64
+    }
+}
+
+/// Maps a resource tab to the item it gathers and how many ticks that
+/// takes. `None` for tabs that have nothing to gather (e.g. the craft tab).
+fn tab_resource(tab: usize) -> Option<(Item, u8)> {
+    match tab {
+        0 => Some((Item::Wood, 8)),
+        1 => Some((Item::Fibre, 6)),
+        2 => Some((Item::Water, 4)),
+        _ => None,
+    }
+}
+
 struct App<'a> {
     pub titles: Vec<&'a str>,
     cur_tab: usize,
     scroll: u8,
     inventory: Vec<(Item, u8)>,
+    recipes: Vec<Recipe>,
+    selected_recipe: usize,
+    jobs: Vec<GatherJob>,
 }
 
 impl<'a> App<'a> {
@@ -45,6 +152,7 @@ impl<'a> App<'a> {
                 "Tab2",
                 "Tab3",
                 "Tab4",
+                "Craft",
             ],
             cur_tab: 0,
             scroll: 0,
@@ -53,6 +161,20 @@ impl<'a> App<'a> {
                 (Item::Fibre, 3),
                 (Item::Water, 13)
             ],
+            recipes: vec![
+                Recipe {
+                    name: "Rope",
+                    inputs: vec![(Item::Fibre, 3)],
+                    output: (Item::Rope, 1),
+                },
+                Recipe {
+                    name: "Campfire",
+                    inputs: vec![(Item::Wood, 5)],
+                    output: (Item::Campfire, 1),
+                },
+            ],
+            selected_recipe: 0,
+            jobs: vec![],
         }
     }
 
@@ -71,10 +193,156 @@ impl<'a> App<'a> {
     fn on_tick(&mut self) {
         self.scroll += 1;
         self.scroll %= 10;
+
+        for job in &mut self.jobs {
+            job.elapsed = (job.elapsed + 1).min(job.duration);
+        }
+        let (done, pending): (Vec<_>, Vec<_>) = self
+            .jobs
+            .drain(..)
+            .partition(|job| job.elapsed >= job.duration);
+        self.jobs = pending;
+        for job in done {
+            self.add_item(job.item, 1);
+        }
+    }
+
+    /// Starts gathering `item`, taking `duration` ticks to complete.
+    fn start_gather(&mut self, item: Item, duration: u8) {
+        self.jobs.push(GatherJob {
+            item,
+            elapsed: 0,
+            duration,
+        });
+    }
+
+    fn next_recipe(&mut self) {
+        if !self.recipes.is_empty() {
+            self.selected_recipe = (self.selected_recipe + 1) % self.recipes.len();
+        }
+    }
+
+    fn previous_recipe(&mut self) {
+        if self.recipes.is_empty() {
+            return;
+        }
+        if self.selected_recipe > 0 {
+            self.selected_recipe -= 1;
+        } else {
+            self.selected_recipe = self.recipes.len() - 1;
+        }
+    }
+
+    fn inventory_qty(&self, item: Item) -> u8 {
+        self.inventory
+            .iter()
+            .find(|(i, _)| *i == item)
+            .map(|(_, qty)| *qty)
+            .unwrap_or(0)
+    }
+
+    fn add_item(&mut self, item: Item, qty: u8) {
+        if let Some(slot) = self.inventory.iter_mut().find(|(i, _)| *i == item) {
+            slot.1 = slot.1.saturating_add(qty);
+        } else {
+            self.inventory.push((item, qty));
+        }
     }
+
+    fn remove_item(&mut self, item: Item, qty: u8) {
+        if let Some(slot) = self.inventory.iter_mut().find(|(i, _)| *i == item) {
+            slot.1 = slot.1.saturating_sub(qty);
+        }
+    }
+
+    fn can_craft(&self, recipe: &Recipe) -> bool {
+        recipe
+            .inputs
+            .iter()
+            .all(|(item, qty)| self.inventory_qty(*item) >= *qty)
+    }
+
+    /// Crafts the currently selected recipe, consuming its inputs and adding
+    /// its output to the inventory. Returns `false` without mutating
+    /// anything if the inputs aren't available.
+    fn craft_selected(&mut self) -> bool {
+        let recipe = match self.recipes.get(self.selected_recipe) {
+            Some(recipe) => recipe.clone(),
+            None => return false,
+        };
+        if !self.can_craft(&recipe) {
+            return false;
+        }
+        for (item, qty) in &recipe.inputs {
+            self.remove_item(*item, *qty);
+        }
+        self.add_item(recipe.output.0, recipe.output.1);
+        true
+    }
+}
+
+/// Serializable snapshot of the parts of `App` worth surviving a restart.
+/// `titles` and `recipes` are left out: they're fixed data rebuilt by
+/// `App::new`, not session state.
+#[cfg(feature = "persistence")]
+#[derive(Serialize, Deserialize)]
+struct AppState {
+    cur_tab: usize,
+    inventory: Vec<(Item, u8)>,
+    jobs: Vec<GatherJob>,
+    selected_recipe: usize,
+}
+
+#[cfg(feature = "persistence")]
+impl<'a> App<'a> {
+    fn save(&self, path: &str) -> io::Result<()> {
+        let state = AppState {
+            cur_tab: self.cur_tab,
+            inventory: self.inventory.clone(),
+            jobs: self.jobs.clone(),
+            selected_recipe: self.selected_recipe,
+        };
+        let json = serde_json::to_string_pretty(&state)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, json)
+    }
+
+    fn load(&mut self, path: &str) -> io::Result<()> {
+        let json = std::fs::read_to_string(path)?;
+        let state: AppState = serde_json::from_str(&json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if state.cur_tab < self.titles.len() {
+            self.cur_tab = state.cur_tab;
+        }
+        self.inventory = state.inventory;
+        self.jobs = state
+            .jobs
+            .into_iter()
+            .filter(|job| job.duration > 0 && job.elapsed <= job.duration)
+            .collect();
+        if state.selected_recipe < self.recipes.len() {
+            self.selected_recipe = state.selected_recipe;
+        }
+        Ok(())
+    }
+}
+
+/// Wraps the default panic hook so a panic mid-frame restores the terminal
+/// (raw mode, alternate screen, cursor) before printing the backtrace.
+/// Without this, a panic inside `ui` or `on_tick` leaves the user's shell
+/// in raw/alternate-screen mode until they run `reset`.
+fn init_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
+        let _ = execute!(io::stdout(), crossterm::cursor::Show);
+        default_hook(panic_info);
+    }));
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
+    init_panic_hook();
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
@@ -83,7 +351,12 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // create app and run it
     let tick_rate = Duration::from_millis(250);
-    let app = App::new();
+    #[allow(unused_mut)]
+    let mut app = App::new();
+    #[cfg(feature = "persistence")]
+    if std::path::Path::new(SAVE_PATH).exists() {
+        let _ = app.load(SAVE_PATH);
+    }
     let res = run_app(&mut terminal, app, tick_rate);
 
     // restore terminal
@@ -109,39 +382,55 @@ fn run_app<B: Backend>(
         mut app: App,
         tick_rate: Duration
     ) -> io::Result<()> {
-    
-    let mut last_tick = Instant::now();
+
+    let events = spawn_input_thread(tick_rate);
 
     loop {
         terminal.draw(|f| ui(f, &app))?;
 
-        let timeout = tick_rate
-            .checked_sub(last_tick.elapsed())
-            .unwrap_or_else(|| Duration::from_secs(0));
-
-        if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => return Ok(()),
-                    KeyCode::Right => app.next(),
-                    KeyCode::Left => app.previous(),
-                    _ => {},
+        match events.recv().expect("input thread disconnected") {
+            Event::Input(key) => match key.code {
+                KeyCode::Char('q') => return Ok(()),
+                KeyCode::Right => app.next(),
+                KeyCode::Left => app.previous(),
+                KeyCode::Up if app.cur_tab == CRAFT_TAB => app.previous_recipe(),
+                KeyCode::Down if app.cur_tab == CRAFT_TAB => app.next_recipe(),
+                KeyCode::Enter if app.cur_tab == CRAFT_TAB => {
+                    app.craft_selected();
                 }
-            }
-        }
-
-        if last_tick.elapsed() >= tick_rate {
-            last_tick = Instant::now();
-            app.on_tick();
+                KeyCode::Char('g') => {
+                    if let Some((item, duration)) = tab_resource(app.cur_tab) {
+                        app.start_gather(item, duration);
+                    }
+                }
+                #[cfg(feature = "persistence")]
+                KeyCode::Char('s') => {
+                    let _ = app.save(SAVE_PATH);
+                }
+                _ => {},
+            },
+            Event::Tick => app.on_tick(),
         }
     }
 }
 
 fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
     let size = f.size();
+    let jobs_height = if app.jobs.is_empty() {
+        0
+    } else {
+        app.jobs.len() as u16 * 3
+    };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(jobs_height),
+            ]
+            .as_ref(),
+        )
         .split(size);
 
     let block = Block::default().style(Style::default().bg(Color::White).fg(Color::Black));
@@ -168,64 +457,131 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
                 .bg(Color::Black),
         );
     f.render_widget(tabs, chunks[0]);
-    let inner = match app.cur_tab {
-        0 => Block::default().title("Inner 0").borders(Borders::ALL),
-        1 => Block::default().title("Inner 1").borders(Borders::ALL),
-        2 => Block::default().title("Inner 2").borders(Borders::ALL),
-        3 => Block::default().title("Inner 3").borders(Borders::ALL),
-        _ => unreachable!(),
-    };
-    f.render_widget(inner, chunks[1]);
-}
 
-/*
-fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
-    let chunks = Layout::default()
+    let content = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(80), Constraint::Percentage(20)].as_ref())
-        .split(f.size());
+        .split(chunks[1]);
 
-    render_inventory(f, &app.inventory, chunks[1]);
+    if app.cur_tab == CRAFT_TAB {
+        render_craft(f, app, content[0]);
+    } else {
+        let inner = match app.cur_tab {
+            0 => Block::default().title("Inner 0").borders(Borders::ALL),
+            1 => Block::default().title("Inner 1").borders(Borders::ALL),
+            2 => Block::default().title("Inner 2").borders(Borders::ALL),
+            3 => Block::default().title("Inner 3").borders(Borders::ALL),
+            _ => unreachable!(),
+        };
+        f.render_widget(inner, content[0]);
+    }
+
+    render_inventory(f, &app.inventory, content[1]);
 
+    if !app.jobs.is_empty() {
+        render_jobs(f, &app.jobs, chunks[2]);
+    }
 }
-*/
 
-fn render_inventory<'a, B: Backend>(f: &mut Frame<B>, inv: &Vec<(Item, u8)>, chunk: Rect) {
-    let create_block = |title| {
+/// Renders one Gauge per active gathering job, stacked vertically, the same
+/// way the tui multi-worker download example shows concurrent progress.
+fn render_jobs<B: Backend>(f: &mut Frame<B>, jobs: &[GatherJob], chunk: Rect) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(jobs.iter().map(|_| Constraint::Length(3)).collect::<Vec<_>>())
+        .split(chunk);
+
+    for (job, row) in jobs.iter().zip(rows.iter()) {
+        let gauge = Gauge::default()
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Gathering {}", job.item.as_str())),
+            )
+            .gauge_style(Style::default().fg(Color::Green).bg(Color::Black))
+            .ratio(job.ratio())
+            .label(format!("{}/{}", job.elapsed, job.duration));
+        f.render_widget(gauge, *row);
+    }
+}
+
+/// Recipe selection screen: lists the known recipes, highlighting the
+/// selected one and greying out any the player can't currently afford.
+fn render_craft<B: Backend>(f: &mut Frame<B>, app: &App, chunk: Rect) {
+    let items: Vec<ListItem> = app
+        .recipes
+        .iter()
+        .enumerate()
+        .map(|(i, recipe)| {
+            let inputs = recipe
+                .inputs
+                .iter()
+                .map(|(item, qty)| format!("{}x{}", qty, item.as_str()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let line = format!("{} <- {}", recipe.name, inputs);
+            let style = if app.can_craft(recipe) {
+                Style::default().fg(Color::Black)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            let style = if i == app.selected_recipe {
+                style.add_modifier(Modifier::BOLD).bg(Color::Gray)
+            } else {
+                style
+            };
+            ListItem::new(Span::styled(line, style))
+        })
+        .collect();
+
+    let list = List::new(items).block(
         Block::default()
             .borders(Borders::ALL)
             .style(Style::default().bg(Color::White).fg(Color::Black))
-            .title(Span::styled(
-                title,
-                Style::default().add_modifier(Modifier::BOLD),
-            ))
-    };
+            .title("Craft (Up/Down select, Enter craft)"),
+    );
+    f.render_widget(list, chunk);
+}
 
-    let mut text = vec![];
+fn render_inventory<B: Backend>(f: &mut Frame<B>, inv: &[(Item, u8)], chunk: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::White).fg(Color::Black))
+        .title(Span::styled(
+            "Inventory",
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+    let inner = block.inner(chunk);
+    f.render_widget(block, chunk);
 
-    for i in 0..inv.len() {
-        text.push(write_inv_item(inv[i]));
+    if inv.is_empty() {
+        return;
     }
 
-    let paragraph = Paragraph::new(text)
-        .style(Style::default().fg(Color::Black).bg(Color::White))
-        .block(create_block("Inventory"))
-        .alignment(Alignment::Right);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(inv.iter().map(|_| Constraint::Length(1)).collect::<Vec<_>>())
+        .split(inner);
 
-    f.render_widget(paragraph, chunk);
+    for ((item, qty), row) in inv.iter().zip(rows.iter()) {
+        f.render_widget(write_inv_item(*item, *qty), *row);
+    }
 }
 
-fn write_inv_item<'a>(item: (Item, u8)) -> Spans<'a> {
-    return Spans::from(vec![
-            Span::styled(item.0.as_str(),
-            Style::default()
-                .add_modifier(Modifier::UNDERLINED)
-            ),
-            Span::raw("     "),
-            Span::styled(item.1.to_string() + "/256", Style::default()
-                .add_modifier(Modifier::ITALIC)
-            ),
-            Span::raw("\n"),
-        ]
-    );
+/// Builds the stack-fullness bar for one inventory slot: green while there's
+/// plenty of headroom, yellow past half, red as it nears the `STACK_CAP`.
+fn write_inv_item<'a>(item: Item, qty: u8) -> LineGauge<'a> {
+    let color = if qty as u16 >= STACK_CAP as u16 * 3 / 4 {
+        Color::Red
+    } else if qty >= STACK_CAP / 2 {
+        Color::Yellow
+    } else {
+        Color::Green
+    };
+
+    LineGauge::default()
+        .gauge_style(Style::default().fg(color).bg(Color::White))
+        .line_set(symbols::line::THICK)
+        .label(format!("{} {}/{}", item.as_str(), qty, STACK_CAP))
+        .ratio(qty as f64 / STACK_CAP as f64)
 }
\ No newline at end of file